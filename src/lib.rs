@@ -5,10 +5,16 @@
 #![deny(missing_docs)]
 #![no_std]
 
+extern crate chrono;
 extern crate embedded_hal as hal;
+extern crate rtcc;
 
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
 use hal::blocking::i2c::{Write, WriteRead};
 
+/// Re-exported so `datetime`/`set_datetime` can be used without depending on `rtcc` directly.
+pub use rtcc::DateTimeAccess;
+
 /// All possible errors in this crate
 #[derive(Debug)]
 pub enum Error<E> {
@@ -33,21 +39,58 @@ pub enum Hours {
 struct Register;
 
 impl Register {
-    const SECONDS : u8 = 0x00;
-    const MINUTES : u8 = 0x01;
-    const HOURS   : u8 = 0x02;
-    const DOW     : u8 = 0x03;
-    const DOM     : u8 = 0x04;
-    const MONTH   : u8 = 0x05;
-    const YEAR    : u8 = 0x06;
+    const SECONDS  : u8 = 0x00;
+    const MINUTES  : u8 = 0x01;
+    const HOURS    : u8 = 0x02;
+    const DOW      : u8 = 0x03;
+    const DOM      : u8 = 0x04;
+    const MONTH    : u8 = 0x05;
+    const YEAR     : u8 = 0x06;
+    const CONTROL  : u8 = 0x07;
+    const RAM_START: u8 = 0x08;
 }
 
+/// Size in bytes of the battery-backed NVRAM.
+const RAM_SIZE: u8 = 56;
+
+/// NVRAM offset reserved for the optional century byte (see `set_century`),
+/// chosen as the last available byte; any `write_ram` that reaches offset 55
+/// overwrites it.
+const CENTURY_RAM_OFFSET: u8 = RAM_SIZE - 1;
+
 struct BitFlags;
 
 impl BitFlags {
     const H24_H12 : u8 = 0b0100_0000;
     const AM_PM   : u8 = 0b0010_0000;
     const CH      : u8 = 0b1000_0000;
+    const OUT     : u8 = 0b1000_0000;
+    const SQWE    : u8 = 0b0001_0000;
+}
+
+/// Square-wave output frequency, selected through the RS1/RS0 bits of the
+/// control register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SquareWaveFrequency {
+    /// 1 Hz
+    Hz1,
+    /// 4.096 kHz
+    Hz4096,
+    /// 8.192 kHz
+    Hz8192,
+    /// 32.768 kHz
+    Hz32768,
+}
+
+impl SquareWaveFrequency {
+    fn rate_select_bits(self) -> u8 {
+        match self {
+            SquareWaveFrequency::Hz1 => 0b00,
+            SquareWaveFrequency::Hz4096 => 0b01,
+            SquareWaveFrequency::Hz8192 => 0b10,
+            SquareWaveFrequency::Hz32768 => 0b11,
+        }
+    }
 }
 
 const DEVICE_ADDRESS: u8 = 0b110_1000;
@@ -185,6 +228,141 @@ where
         self.write_register_decimal(Register::YEAR, (year - 2000) as u8)
     }
 
+    /// Halts the oscillator, stopping the clock.
+    /// The DS1307 ships from the factory with the oscillator halted, so this
+    /// must be undone with `run()` before the clock will keep time.
+    pub fn halt(&mut self) -> Result<(), Error<E>> {
+        self.set_running(false)
+    }
+
+    /// Starts the oscillator, so the clock begins keeping time.
+    pub fn run(&mut self) -> Result<(), Error<E>> {
+        self.set_running(true)
+    }
+
+    /// Sets whether the oscillator is running, without disturbing the
+    /// current seconds value.
+    pub fn set_running(&mut self, running: bool) -> Result<(), Error<E>> {
+        let data = self.read_register(Register::SECONDS)?;
+        let data = if running {
+            data & !BitFlags::CH
+        } else {
+            data | BitFlags::CH
+        };
+        self.write_register(Register::SECONDS, data)
+    }
+
+    /// Returns whether the oscillator is running (i.e. the CH bit is clear).
+    pub fn is_running(&mut self) -> Result<bool, Error<E>> {
+        let data = self.read_register(Register::SECONDS)?;
+        Ok(data & BitFlags::CH == 0)
+    }
+
+    /// Drives the SQW/OUT pin with a square wave of the given frequency.
+    pub fn enable_square_wave(&mut self, frequency: SquareWaveFrequency) -> Result<(), Error<E>> {
+        let data = self.read_register(Register::CONTROL)?;
+        let data = (data & BitFlags::OUT) | BitFlags::SQWE | frequency.rate_select_bits();
+        self.write_register(Register::CONTROL, data)
+    }
+
+    /// Stops the square wave output, so the SQW/OUT pin is driven by the
+    /// static level set with `set_output_level()` instead.
+    pub fn disable_square_wave(&mut self) -> Result<(), Error<E>> {
+        let data = self.read_register(Register::CONTROL)?;
+        self.write_register(Register::CONTROL, data & !BitFlags::SQWE)
+    }
+
+    /// Sets the level driven on the SQW/OUT pin while the square wave output
+    /// is disabled.
+    pub fn set_output_level(&mut self, high: bool) -> Result<(), Error<E>> {
+        let data = self.read_register(Register::CONTROL)?;
+        let data = if high {
+            data | BitFlags::OUT
+        } else {
+            data & !BitFlags::OUT
+        };
+        self.write_register(Register::CONTROL, data)
+    }
+
+    /// Reads `buf.len()` bytes of the battery-backed NVRAM starting at
+    /// `offset` (0-55) into `buf`, in a single burst read.
+    /// Will throw an InvalidInputData error if the requested range does not
+    /// fit inside the 56 bytes of NVRAM.
+    pub fn read_ram(&mut self, offset: u8, buf: &mut [u8]) -> Result<(), Error<E>> {
+        if offset as usize + buf.len() > RAM_SIZE as usize {
+            return Err(Error::InvalidInputData);
+        }
+        self.i2c
+            .write_read(DEVICE_ADDRESS, &[Register::RAM_START + offset], buf)
+            .map_err(Error::I2C)
+    }
+
+    /// Writes `data` to the battery-backed NVRAM starting at `offset`
+    /// (0-55), in a single burst write.
+    /// Will throw an InvalidInputData error if the requested range does not
+    /// fit inside the 56 bytes of NVRAM.
+    pub fn write_ram(&mut self, offset: u8, data: &[u8]) -> Result<(), Error<E>> {
+        if offset as usize + data.len() > RAM_SIZE as usize {
+            return Err(Error::InvalidInputData);
+        }
+        let mut payload = [0; 1 + RAM_SIZE as usize];
+        payload[0] = Register::RAM_START + offset;
+        payload[1..=data.len()].copy_from_slice(data);
+        self.i2c
+            .write(DEVICE_ADDRESS, &payload[..=data.len()])
+            .map_err(Error::I2C)
+    }
+
+    /// Stores the century (e.g. 19, 20, 21) in a reserved NVRAM byte, so
+    /// `get_year_with_century`/`set_year_with_century` can track years
+    /// outside the 2000-2099 range the bare year register covers.
+    ///
+    /// This is independent of `get_year`/`set_year` and of the
+    /// `DateTimeAccess` impl (`datetime`/`set_datetime`): neither consults
+    /// the stored century, so they remain fixed to the 2000-2099 window
+    /// for backward compatibility. Callers who need a century-aware whole
+    /// date/time must combine `get_year_with_century`/`set_year_with_century`
+    /// with the other field accessors themselves.
+    pub fn set_century(&mut self, century: u8) -> Result<(), Error<E>> {
+        self.write_ram(CENTURY_RAM_OFFSET, &[century])
+    }
+
+    /// Reads back the century previously stored with `set_century`.
+    pub fn get_century(&mut self) -> Result<u8, Error<E>> {
+        let mut data = [0];
+        self.read_ram(CENTURY_RAM_OFFSET, &mut data)?;
+        Ok(data[0])
+    }
+
+    /// Reads the year, combining the BCD year register with the century
+    /// stored by `set_century`, so the result is not limited to 2000-2099.
+    /// If the year register has rolled over from 99 to 00 since the century
+    /// was last stored, the caller is responsible for detecting that and
+    /// calling `set_century` with the incremented value.
+    ///
+    /// This does not affect `datetime()`: that still reports years as
+    /// 2000 + the bare year register, regardless of the stored century.
+    pub fn get_year_with_century(&mut self) -> Result<u16, Error<E>> {
+        let century = self.get_century()?;
+        let year = self.read_register_decimal(Register::YEAR)?;
+        Ok(u16::from(century) * 100 + u16::from(year))
+    }
+
+    /// Sets the year, splitting it into a century (stored in NVRAM via
+    /// `set_century`) and the two-digit BCD year register.
+    /// Will throw an InvalidInputData error if `year` does not fit in a
+    /// century byte (0-255) plus a two-digit year, i.e. if `year > 25599`.
+    ///
+    /// This does not affect `set_datetime()`: that still rejects any year
+    /// outside 2000-2099, regardless of the stored century.
+    pub fn set_year_with_century(&mut self, year: u16) -> Result<(), Error<E>> {
+        if year / 100 > u16::from(u8::MAX) {
+            return Err(Error::InvalidInputData);
+        }
+        self.set_century((year / 100) as u8)?;
+        self.write_register_decimal(Register::YEAR, (year % 100) as u8)
+    }
+
     fn write_register_decimal(&mut self, register: u8, decimal_number: u8) -> Result<(), Error<E>> {
         self.write_register(register, decimal_to_packed_bcd(decimal_number))
     }
@@ -210,6 +388,87 @@ where
     }
 }
 
+impl<I2C, E> DateTimeAccess for DS1307<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    type Error = Error<E>;
+
+    /// Reads the whole date and time in a single burst read of registers
+    /// 0x00-0x06, so a seconds-to-minutes rollover cannot corrupt the
+    /// result the way reading each register separately could.
+    fn datetime(&mut self) -> Result<NaiveDateTime, Self::Error> {
+        let mut data = [0; 7];
+        self.i2c
+            .write_read(DEVICE_ADDRESS, &[Register::SECONDS], &mut data)
+            .map_err(Error::I2C)?;
+        let seconds = packed_bcd_to_decimal(remove_ch_bit(data[0]));
+        let minutes = packed_bcd_to_decimal(data[1]);
+        let hours = hours_from_register(data[2]);
+        let day = packed_bcd_to_decimal(data[4]);
+        let month = packed_bcd_to_decimal(data[5]);
+        let year = 2000 + i32::from(packed_bcd_to_decimal(data[6]));
+
+        NaiveDate::from_ymd_opt(year, u32::from(month), u32::from(day))
+            .and_then(|date| {
+                date.and_hms_opt(u32::from(hours), u32::from(minutes), u32::from(seconds))
+            })
+            .ok_or(Error::InvalidInputData)
+    }
+
+    /// Writes the whole date and time in a single burst write of registers
+    /// 0x00-0x06, preserving the CH (clock halt) bit of the seconds register.
+    ///
+    /// The day-of-week register is derived from `datetime` using a
+    /// Monday=1..Sunday=7 numbering and will overwrite any value
+    /// previously set through `set_day_of_week`, which otherwise treats
+    /// day-of-week as an opaque 1-7 value with no numbering convention of
+    /// its own.
+    fn set_datetime(&mut self, datetime: &NaiveDateTime) -> Result<(), Self::Error> {
+        let date = datetime.date();
+        let year = date.year();
+        if year < 2000 || year > 2099 {
+            return Err(Error::InvalidInputData);
+        }
+        let ch = self.read_register(Register::SECONDS)? & BitFlags::CH;
+        let payload = [
+            Register::SECONDS,
+            ch | decimal_to_packed_bcd(datetime.second() as u8),
+            decimal_to_packed_bcd(datetime.minute() as u8),
+            decimal_to_packed_bcd(datetime.hour() as u8),
+            decimal_to_packed_bcd(datetime.weekday().number_from_monday() as u8),
+            decimal_to_packed_bcd(date.day() as u8),
+            decimal_to_packed_bcd(date.month() as u8),
+            decimal_to_packed_bcd((year - 2000) as u8),
+        ];
+        self.i2c
+            .write(DEVICE_ADDRESS, &payload)
+            .map_err(Error::I2C)
+    }
+}
+
+/// Decodes an hours register byte (in either 12h or 24h format) to a plain
+/// 24-hour value, for use where a single numeric hour is needed (e.g. to
+/// build a `chrono` time).
+fn hours_from_register(data: u8) -> u8 {
+    if is_24h_format(data) {
+        packed_bcd_to_decimal(data & !BitFlags::H24_H12)
+    } else {
+        let h = packed_bcd_to_decimal(data & !(BitFlags::H24_H12 | BitFlags::AM_PM));
+        if is_am(data) {
+            if h == 12 {
+                0
+            } else {
+                h
+            }
+        } else if h == 12 {
+            12
+        } else {
+            h + 12
+        }
+    }
+}
+
 fn is_24h_format(hours_data: u8) -> bool {
     hours_data & BitFlags::H24_H12 == 0
 }
@@ -293,6 +552,193 @@ mod tests {
         check_sent_data(rtc, &[Register::SECONDS, 0b1101_1001]);
     }
 
+    #[test]
+    fn can_read_datetime() {
+        let mut rtc = setup(&[
+            0b0101_1001, 0b0101_1001, 0b0010_0011, 7, 0b0011_0001, 0b0001_0010, 0b1001_1001,
+        ]);
+        let datetime = rtc.datetime().unwrap();
+        assert_eq!(
+            datetime,
+            NaiveDate::from_ymd_opt(2099, 12, 31)
+                .unwrap()
+                .and_hms_opt(23, 59, 59)
+                .unwrap()
+        );
+        check_sent_data(rtc, &[Register::SECONDS]);
+    }
+
+    #[test]
+    fn invalid_datetime_register_values_return_error() {
+        // month = 13
+        let mut rtc = setup(&[0, 0, 0, 1, 1, 0b0001_0011, 0]);
+        match rtc.datetime() {
+            Err(Error::InvalidInputData) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn can_write_datetime() {
+        let mut rtc = setup(&[0b1000_0000]);
+        let datetime = NaiveDate::from_ymd_opt(2020, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        rtc.set_datetime(&datetime).unwrap();
+        check_sent_data(
+            rtc,
+            &[Register::SECONDS, 0b1000_0000, 0, 0, 3, 1, 1, 0b0010_0000],
+        );
+    }
+
+    #[test]
+    fn datetime_year_out_of_range_returns_error() {
+        let mut rtc = setup(&[0]);
+        let datetime = NaiveDate::from_ymd_opt(1999, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        match rtc.set_datetime(&datetime) {
+            Err(Error::InvalidInputData) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn can_halt() {
+        let mut rtc = setup(&[0b0101_1001]);
+        rtc.halt().unwrap();
+        check_sent_data(rtc, &[Register::SECONDS, 0b1101_1001]);
+    }
+
+    #[test]
+    fn can_run() {
+        let mut rtc = setup(&[0b1101_1001]);
+        rtc.run().unwrap();
+        check_sent_data(rtc, &[Register::SECONDS, 0b0101_1001]);
+    }
+
+    #[test]
+    fn is_running_when_ch_bit_clear() {
+        let mut rtc = setup(&[0b0101_1001]);
+        assert!(rtc.is_running().unwrap());
+    }
+
+    #[test]
+    fn is_not_running_when_ch_bit_set() {
+        let mut rtc = setup(&[0b1101_1001]);
+        assert!(!rtc.is_running().unwrap());
+    }
+
+    #[test]
+    fn can_enable_square_wave() {
+        let mut rtc = setup(&[0]);
+        rtc.enable_square_wave(SquareWaveFrequency::Hz4096).unwrap();
+        check_sent_data(rtc, &[Register::CONTROL, 0b0001_0001]);
+    }
+
+    #[test]
+    fn enabling_square_wave_keeps_out_level() {
+        let mut rtc = setup(&[0b1000_0000]);
+        rtc.enable_square_wave(SquareWaveFrequency::Hz32768).unwrap();
+        check_sent_data(rtc, &[Register::CONTROL, 0b1001_0011]);
+    }
+
+    #[test]
+    fn can_disable_square_wave() {
+        let mut rtc = setup(&[0b0001_0001]);
+        rtc.disable_square_wave().unwrap();
+        check_sent_data(rtc, &[Register::CONTROL, 0b0000_0001]);
+    }
+
+    #[test]
+    fn can_set_output_level_high() {
+        let mut rtc = setup(&[0]);
+        rtc.set_output_level(true).unwrap();
+        check_sent_data(rtc, &[Register::CONTROL, 0b1000_0000]);
+    }
+
+    #[test]
+    fn can_set_output_level_low() {
+        let mut rtc = setup(&[0b1000_0000]);
+        rtc.set_output_level(false).unwrap();
+        check_sent_data(rtc, &[Register::CONTROL, 0]);
+    }
+
+    #[test]
+    fn can_read_ram() {
+        let mut rtc = setup(&[1, 2, 3]);
+        let mut buf = [0; 3];
+        rtc.read_ram(2, &mut buf).unwrap();
+        assert_eq!([1, 2, 3], buf);
+        check_sent_data(rtc, &[Register::RAM_START + 2]);
+    }
+
+    #[test]
+    fn read_ram_out_of_range_returns_error() {
+        let mut rtc = setup(&[0]);
+        let mut buf = [0; 4];
+        match rtc.read_ram(53, &mut buf) {
+            Err(Error::InvalidInputData) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn can_write_ram() {
+        let mut rtc = setup(&[0]);
+        rtc.write_ram(2, &[1, 2, 3]).unwrap();
+        check_sent_data(rtc, &[Register::RAM_START + 2, 1, 2, 3]);
+    }
+
+    #[test]
+    fn write_ram_out_of_range_returns_error() {
+        let mut rtc = setup(&[0]);
+        match rtc.write_ram(53, &[1, 2, 3, 4]) {
+            Err(Error::InvalidInputData) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn can_set_century() {
+        let mut rtc = setup(&[0]);
+        rtc.set_century(20).unwrap();
+        check_sent_data(rtc, &[Register::RAM_START + CENTURY_RAM_OFFSET, 20]);
+    }
+
+    #[test]
+    fn can_get_century() {
+        let mut rtc = setup(&[20]);
+        assert_eq!(20, rtc.get_century().unwrap());
+        check_sent_data(rtc, &[Register::RAM_START + CENTURY_RAM_OFFSET]);
+    }
+
+    #[test]
+    fn can_get_year_with_century() {
+        // The century byte and the year register both read back as 21
+        // (0x15 in BCD, i.e. year 15).
+        let mut rtc = setup(&[21, 21]);
+        assert_eq!(2115, rtc.get_year_with_century().unwrap());
+    }
+
+    #[test]
+    fn can_set_year_with_century() {
+        let mut rtc = setup(&[0]);
+        rtc.set_year_with_century(1999).unwrap();
+        check_sent_data(rtc, &[Register::YEAR, 0b1001_1001]);
+    }
+
+    #[test]
+    fn set_year_with_century_out_of_range_returns_error() {
+        let mut rtc = setup(&[0]);
+        match rtc.set_year_with_century(25600) {
+            Err(Error::InvalidInputData) => (),
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn can_read_minutes() {
         let mut rtc = setup(&[0b0101_1001]);